@@ -0,0 +1,345 @@
+use core::future::Future;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use crate::error::EepromError;
+use crate::key::Key;
+use crate::record::{Record, scan_record, encode_header, header_len, dead_marker};
+use crate::PageHeader::{Erased, Active, GcRunning};
+use crate::{KeyIndex, insert_into_index, lookup_in_index};
+
+/// Async counterpart to [`EEPROM`](crate::EEPROM) for flash parts that only
+/// expose asynchronous read/erase/program operations (the shape of
+/// `embedded-storage-async`), rather than a directly addressable memory
+/// region.
+///
+/// `PAGE_SIZE` bounds the page buffer the default methods stage reads and
+/// writes through, since there is no memory-mapped slice to borrow from
+/// while an operation is in flight. Methods use native `async fn` in trait
+/// position rather than boxing futures, since this crate is `no_std`
+/// without an allocator.
+pub trait AsyncEEPROM<const N: usize, const PAGE_SIZE: usize> {
+    /// Reads the entire page `index` into `buf`.
+    async fn read_page(&mut self, index: usize, buf: &mut [u8; PAGE_SIZE]) -> Result<(), EepromError>;
+
+    /// Writes `data` into page `index` at `offset`.
+    async fn write_bytes(&mut self, index: usize, offset: usize, data: &[u8]) -> Result<(), EepromError>;
+
+    /// Erases page `index` back to its erased state.
+    async fn erase_page(&mut self, index: usize) -> Result<(), EepromError>;
+
+    /// Returns the in-RAM [`KeyIndex`], for implementors that keep one.
+    ///
+    /// The default returns `None`, which makes the index an opt-in feature:
+    /// `read_variable` falls back to scanning the active page when there is
+    /// no index to consult, or when the index doesn't have the key cached.
+    /// `write_variable`, `run_garbage_collection`, and `recover_gc` keep
+    /// whatever index is here in sync incrementally as they run, pointing
+    /// each entry at a record's current offset rather than rescanning the
+    /// whole page after every change.
+    fn index(&self) -> Option<&KeyIndex> {
+        None
+    }
+
+    /// Mutable counterpart to [`index`](Self::index).
+    fn index_mut(&mut self) -> Option<&mut KeyIndex> {
+        None
+    }
+
+    /// Resumes or rolls back an interrupted [`run_garbage_collection`](Self::run_garbage_collection),
+    /// mirroring [`EEPROM::recover_gc`](crate::EEPROM::recover_gc).
+    async fn recover_gc(&mut self) -> Result<(), EepromError> {
+        let mut gc_page_index = None;
+
+        for index in 0..N {
+            let mut header = [0u8; 1];
+            self.read_page_prefix(index, &mut header).await?;
+
+            if header[0] == GcRunning as u8 {
+                gc_page_index = Some(index);
+                break;
+            }
+        }
+
+        let gc_page_index = match gc_page_index {
+            Some(n) => n,
+            None => return Ok(()),
+        };
+
+        let dest_page_index = if gc_page_index + 1 == N { 0 } else { gc_page_index + 1 };
+
+        let mut dest_header = [0u8; 1];
+        self.read_page_prefix(dest_page_index, &mut dest_header).await?;
+
+        if dest_header[0] == Active as u8 {
+            // The copy had already finished; only erasing the old page was interrupted.
+            self.erase_page(gc_page_index).await?;
+            return Ok(());
+        }
+
+        let mut dest_buf = [0u8; PAGE_SIZE];
+        self.read_page(dest_page_index, &mut dest_buf).await?;
+
+        // Find how far the interrupted copy got by re-validating the destination's records.
+        let mut resume_at = 1;
+        loop {
+            match scan_record(&dest_buf, resume_at) {
+                Record::End => break,
+                Record::Dead { next_index } | Record::Live { next_index, .. } => resume_at = next_index,
+            }
+        }
+
+        let mut active_buf = [0u8; PAGE_SIZE];
+        self.read_page(gc_page_index, &mut active_buf).await?;
+
+        let mut active_index = 1;
+        let mut dest_index = 1;
+
+        loop {
+            match scan_record(&active_buf, active_index) {
+                Record::End => break,
+                Record::Dead { next_index } => active_index = next_index,
+                Record::Live { key, next_index, .. } => {
+                    if dest_index >= resume_at {
+                        let record = &active_buf[active_index..next_index];
+                        self.write_bytes(dest_page_index, dest_index, record).await?;
+                    }
+                    // Whether or not this record needed rewriting, it's
+                    // landing at `dest_index` in the recovered page, so the
+                    // index has to point there too.
+                    if let Some(table) = self.index_mut() {
+                        insert_into_index(table, key, dest_index as u32);
+                    }
+                    dest_index += next_index - active_index;
+                    active_index = next_index;
+                }
+            }
+        }
+
+        self.write_bytes(dest_page_index, 0, &[Active as u8]).await?;
+        self.erase_page(gc_page_index).await?;
+
+        Ok(())
+    }
+
+    async fn run_garbage_collection(&mut self) -> Result<usize, EepromError> {
+        let active_page_index = if let Some(n) = self.find_active_page().await? {
+            n
+        } else {
+            for i in 0..N {
+                self.erase_page(i).await?;
+            }
+            self.write_bytes(0, 0, &[Active as u8]).await?;
+            0
+        };
+
+        let next_page_index = if active_page_index + 1 == N { 0 } else { active_page_index + 1 };
+
+        let mut next_header = [0u8; 1];
+        self.read_page_prefix(next_page_index, &mut next_header).await?;
+        if next_header[0] != Erased as u8 {
+            return Err(EepromError::CorruptPageHeader { index: next_page_index, value: next_header[0] });
+        }
+
+        self.write_bytes(active_page_index, 0, &[GcRunning as u8]).await?;
+
+        let mut active_buf = [0u8; PAGE_SIZE];
+        self.read_page(active_page_index, &mut active_buf).await?;
+
+        let mut active_index = 1;
+        let mut next_index = 1;
+
+        loop {
+            match scan_record(&active_buf, active_index) {
+                Record::End => break,
+                Record::Dead { next_index: n } => active_index = n,
+                Record::Live { key, next_index: n, .. } => {
+                    let record = &active_buf[active_index..n];
+                    let dest_offset = next_index;
+                    self.write_bytes(next_page_index, dest_offset, record).await?;
+                    if let Some(table) = self.index_mut() {
+                        insert_into_index(table, key, dest_offset as u32);
+                    }
+                    next_index += n - active_index;
+                    active_index = n;
+                }
+            }
+        }
+
+        // Mark the new page active *before* erasing the old one. If a power
+        // loss tears the erase, the old page's `GcRunning` header can end up
+        // partly wiped - but by then the new page is already a valid `Active`
+        // page on its own, so nothing is ever lost between the two steps.
+        // The reverse order has a window where neither page is recognizable:
+        // the old page's marker gone, the new one not yet set.
+        self.write_bytes(next_page_index, 0, &[Active as u8]).await?;
+        self.erase_page(active_page_index).await?;
+
+        Ok(next_page_index)
+    }
+
+    async fn write_variable(&mut self, key: Key, data: &[u8]) -> Result<(), EepromError> {
+        self.recover_gc().await?;
+
+        if header_len(key.len()) + data.len() > PAGE_SIZE {
+            return Err(EepromError::VariableTooLarge);
+        }
+
+        let active_page_index = if let Some(n) = self.find_active_page().await? {
+            n
+        } else {
+            for i in 0..N {
+                self.erase_page(i).await?;
+            }
+            self.write_bytes(0, 0, &[Active as u8]).await?;
+            0
+        };
+
+        let mut page_index = active_page_index;
+        let mut buf = [0u8; PAGE_SIZE];
+        self.read_page(page_index, &mut buf).await?;
+
+        let mut index = 1;
+        let mut gc_run = false;
+
+        loop {
+            if index + header_len(key.len()) + data.len() > PAGE_SIZE {
+                if gc_run {
+                    return Err(EepromError::OutOfSpace);
+                }
+
+                page_index = self.run_garbage_collection().await?;
+                self.read_page(page_index, &mut buf).await?;
+                index = 1;
+                gc_run = true;
+                continue;
+            }
+
+            match scan_record(&buf, index) {
+                Record::End => {
+                    let (header, used) = encode_header(&key, data);
+                    self.write_bytes(page_index, index, &header[..used]).await?;
+                    self.write_bytes(page_index, index + used, data).await?;
+                    // Supersedes whatever offset (if any) this key was
+                    // previously cached at - the old record was just
+                    // tombstoned above, if it existed.
+                    if let Some(table) = self.index_mut() {
+                        insert_into_index(table, key, index as u32);
+                    }
+                    return Ok(());
+                }
+                Record::Dead { next_index } => index = next_index,
+                Record::Live { key: existing, next_index, .. } => {
+                    if existing == key {
+                        self.write_bytes(page_index, index, &[dead_marker(existing.len())]).await?;
+                    }
+                    index = next_index;
+                }
+            }
+        }
+    }
+
+    async fn read_variable(&mut self, key: Key, out: &mut [u8]) -> Result<Option<usize>, EepromError> {
+        self.recover_gc().await?;
+
+        let active_page_index = match self.find_active_page().await? {
+            Some(n) => n,
+            None => return Err(EepromError::NoActivePage),
+        };
+
+        let mut buf = [0u8; PAGE_SIZE];
+        self.read_page(active_page_index, &mut buf).await?;
+
+        if let Some(table) = self.index() {
+            if let Some(offset) = lookup_in_index(table, key) {
+                let offset = offset as usize;
+
+                if let Record::Live { key: found, size, .. } = scan_record(&buf, offset) {
+                    if found == key {
+                        let hl = header_len(found.len());
+                        let len = core::cmp::min(out.len(), size);
+                        out[..len].copy_from_slice(&buf[offset + hl..offset + hl + len]);
+                        return Ok(Some(len));
+                    }
+                }
+                // The indexed offset didn't check out (e.g. the index wasn't
+                // rebuilt after an out-of-band page change) - fall back to
+                // the scan-based path below.
+            }
+            // Not cached doesn't mean absent: the index only remembers up to
+            // INDEX_CAPACITY keys, so a miss here still has to be confirmed
+            // by scanning the page.
+        }
+
+        let mut index = 1;
+
+        loop {
+            match scan_record(&buf, index) {
+                Record::End => return Ok(None),
+                Record::Dead { next_index } => index = next_index,
+                Record::Live { key: existing, size, next_index } => {
+                    if existing == key {
+                        let hl = header_len(existing.len());
+                        let len = core::cmp::min(out.len(), size);
+                        out[..len].copy_from_slice(&buf[index + hl..index + hl + len]);
+                        return Ok(Some(len));
+                    }
+                    index = next_index;
+                }
+            }
+        }
+    }
+
+    /// Reads just the leading bytes of a page, for inspecting the header
+    /// without staging the whole page.
+    async fn read_page_prefix(&mut self, index: usize, buf: &mut [u8]) -> Result<(), EepromError> {
+        let mut page = [0u8; PAGE_SIZE];
+        self.read_page(index, &mut page).await?;
+        buf.copy_from_slice(&page[..buf.len()]);
+        Ok(())
+    }
+
+    /// Scans the pages for the one marked `Active`.
+    async fn find_active_page(&mut self) -> Result<Option<usize>, EepromError> {
+        for index in 0..N {
+            let mut header = [0u8; 1];
+            self.read_page_prefix(index, &mut header).await?;
+
+            match header[0] {
+                core::u8::MAX => continue,
+                1 => return Ok(Some(index)),
+                value => return Err(EepromError::CorruptPageHeader { index, value })
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+static NOOP_VTABLE: RawWakerVTable = RawWakerVTable::new(
+    |_| RawWaker::new(core::ptr::null(), &NOOP_VTABLE),
+    |_| {},
+    |_| {},
+    |_| {},
+);
+
+/// Drives a future to completion by polling it in a tight loop.
+///
+/// This lets a bare blocking loop (no cooperative executor) use an
+/// [`AsyncEEPROM`] implementation: the future is expected to make progress
+/// on every poll, since flash operations on most parts are driven to
+/// completion synchronously under the hood even when exposed through an
+/// async interface.
+pub fn block_on<F: Future>(mut fut: F) -> F::Output {
+    let raw_waker = RawWaker::new(core::ptr::null(), &NOOP_VTABLE);
+    let waker = unsafe { Waker::from_raw(raw_waker) };
+    let mut cx = Context::from_waker(&waker);
+
+    // SAFETY: `fut` is not moved again after being pinned here.
+    let mut fut = unsafe { core::pin::Pin::new_unchecked(&mut fut) };
+
+    loop {
+        if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
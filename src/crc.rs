@@ -0,0 +1,22 @@
+/// Minimal bitwise CRC32 (IEEE 802.3 polynomial), computed over one or more
+/// byte slices in sequence.
+///
+/// Kept in-crate instead of pulling in a CRC dependency, since the only
+/// external dependency so far is `byteorder` for the header encoding.
+pub(crate) fn crc32(chunks: &[&[u8]]) -> u32 {
+    const POLY: u32 = 0xEDB88320;
+
+    let mut crc = 0xFFFF_FFFFu32;
+
+    for chunk in chunks {
+        for &byte in *chunk {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (crc & 1).wrapping_neg();
+                crc = (crc >> 1) ^ (POLY & mask);
+            }
+        }
+    }
+
+    !crc
+}
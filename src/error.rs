@@ -0,0 +1,24 @@
+/// Errors that can occur while reading, writing, or reclaiming space in an
+/// [`EEPROM`](crate::EEPROM) store.
+///
+/// Every fallible operation on the trait returns one of these instead of
+/// panicking, since a panic on a microcontroller aborts the firmware rather
+/// than giving the caller a chance to recover (for example by reformatting
+/// the flash region).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EepromError {
+    /// There is no room left for the variable even after running garbage
+    /// collection.
+    OutOfSpace,
+    /// A page header byte did not match any known [`PageHeader`](crate::PageHeader)
+    /// value.
+    CorruptPageHeader { index: usize, value: u8 },
+    /// A key was empty or longer than [`MAX_KEY_LEN`](crate::MAX_KEY_LEN);
+    /// both are reserved by the record format as sentinel lengths.
+    InvalidKey,
+    /// The variable is larger than a page can ever hold, regardless of how
+    /// much space garbage collection is able to reclaim.
+    VariableTooLarge,
+    /// No page is currently marked `Active`.
+    NoActivePage,
+}
@@ -0,0 +1,67 @@
+use crate::error::EepromError;
+
+/// Maximum number of bytes a [`Key`] may hold.
+pub const MAX_KEY_LEN: usize = 12;
+
+/// A small, fixed-capacity byte key identifying a variable in the store.
+///
+/// Keys are 1 to [`MAX_KEY_LEN`] bytes, stored inline in the record header
+/// alongside an explicit length byte rather than reusing a fixed-width
+/// numeric address. A length of `0` is treated as a corrupt header (same as
+/// an erased `0xFF` byte), and the length byte's top bit is reserved to mark
+/// a record live or dead, so [`Key::new`] rejects an empty or oversized key
+/// up front instead of letting it collide with those sentinels later.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Key {
+    bytes: [u8; MAX_KEY_LEN],
+    len: u8,
+}
+
+impl Key {
+    /// Builds a key from up to [`MAX_KEY_LEN`] bytes.
+    pub fn new(bytes: &[u8]) -> Result<Key, EepromError> {
+        if bytes.is_empty() || bytes.len() > MAX_KEY_LEN {
+            return Err(EepromError::InvalidKey);
+        }
+
+        let mut buf = [0u8; MAX_KEY_LEN];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        Ok(Key { bytes: buf, len: bytes.len() as u8 })
+    }
+
+    /// Builds a key from bytes already known to be within `1..=MAX_KEY_LEN`,
+    /// skipping the validation `new` does - for a record header already
+    /// verified by [`scan_record`](crate::record::scan_record), or bytes
+    /// produced by one of the `From` impls below.
+    pub(crate) fn from_validated(bytes: &[u8]) -> Key {
+        let mut buf = [0u8; MAX_KEY_LEN];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        Key { bytes: buf, len: bytes.len() as u8 }
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    pub(crate) fn len(&self) -> u8 {
+        self.len
+    }
+}
+
+impl From<u8> for Key {
+    fn from(value: u8) -> Key {
+        Key::from_validated(&[value])
+    }
+}
+
+impl From<u16> for Key {
+    fn from(value: u16) -> Key {
+        Key::from_validated(&value.to_le_bytes())
+    }
+}
+
+impl From<u32> for Key {
+    fn from(value: u32) -> Key {
+        Key::from_validated(&value.to_le_bytes())
+    }
+}
@@ -1,10 +1,21 @@
 #![no_std]
 #![feature(const_generics)]
 #![allow(incomplete_features)]
+#![allow(async_fn_in_trait)]
 
-use byteorder::{ByteOrder, LittleEndian};
+mod error;
+mod crc;
+mod record;
+mod key;
+mod async_eeprom;
+mod mock_flash;
 
-use crate::PageHeader::{Erased, Active, GcRunning};
+pub use error::EepromError;
+pub use key::{Key, MAX_KEY_LEN};
+pub use async_eeprom::{AsyncEEPROM, block_on};
+pub use mock_flash::MockFlash;
+
+use crate::record::{Record, scan_record, header_len};
 
 #[repr(u8)]
 enum PageHeader {
@@ -13,38 +24,87 @@ enum PageHeader {
     GcRunning = 0,
 }
 
-struct Variable {
-    address: u8,
-    size: u32,
-}
-
-impl Into<[u8; 5]> for Variable {
-    fn into(self) -> [u8; 5] {
-        let address = self.address;
-        let size = self.size.to_le_bytes();
+/// How many distinct keys a [`KeyIndex`] can cache at once.
+///
+/// Unlike the old single-byte address space, the key space is now far too
+/// large to index with a flat lookup table, so the index is instead a small
+/// fixed-capacity table consulted on a best-effort basis: a miss just falls
+/// back to scanning the page, it doesn't mean the key is absent.
+pub const INDEX_CAPACITY: usize = 64;
+
+/// Caches the byte offset of each of up to [`INDEX_CAPACITY`] live keys'
+/// latest record in the active page.
+pub type KeyIndex = [Option<(Key, u32)>; INDEX_CAPACITY];
+
+/// Inserts or updates `key`'s offset in `table`. Silently drops the update
+/// if `table` is full and `key` wasn't already present - callers fall back
+/// to scanning the page for a key the index doesn't have room for.
+pub(crate) fn insert_into_index(table: &mut KeyIndex, key: Key, offset: u32) {
+    for slot in table.iter_mut() {
+        if matches!(slot, Some((k, _)) if *k == key) {
+            *slot = Some((key, offset));
+            return;
+        }
+    }
 
-        [address, size[0], size[1], size[2], size[3]]
+    if let Some(slot) = table.iter_mut().find(|slot| slot.is_none()) {
+        *slot = Some((key, offset));
     }
 }
 
-impl Into<Variable> for &[u8] {
-    fn into(self) -> Variable {
-        assert_eq!(self.len(), 5);
-
-        Variable {
-            address: self[0],
-            size: LittleEndian::read_u32(&self[1..5]),
-        }
-    }
+/// Looks up `key`'s cached offset in `table`, if present.
+pub(crate) fn lookup_in_index(table: &KeyIndex, key: Key) -> Option<u32> {
+    table.iter().find_map(|slot| match slot {
+        Some((k, offset)) if *k == key => Some(*offset),
+        _ => None,
+    })
 }
 
-pub trait EEPROM<const N: usize> {
-    unsafe fn get_pages(&self) -> [&[u8]; N];
-    unsafe fn get_pages_mut(&mut self) -> [&mut [u8]; N];
+/// Blocking counterpart to [`AsyncEEPROM`] for flash that's a directly
+/// addressable memory region, so callers can read and write without going
+/// through an executor.
+///
+/// `get_pages`/`get_pages_mut`/`reset_page` are the only methods an
+/// implementor has to provide; every other method has a default. `index` and
+/// `index_mut` return `None`; the rest are built on top of the three, either
+/// directly (`rebuild_index`, and `read_variable`, which needs to return a
+/// reference borrowed from the page itself) or by driving the identical
+/// [`AsyncEEPROM`] logic to completion with [`block_on`] (everything else),
+/// so the two traits share one implementation of the garbage-collection
+/// logic instead of keeping two copies in sync by hand.
+pub trait EEPROM<const N: usize, const PAGE_SIZE: usize> {
+    unsafe fn get_pages(&self) -> [&[u8; PAGE_SIZE]; N];
+    unsafe fn get_pages_mut(&mut self) -> [&mut [u8; PAGE_SIZE]; N];
     unsafe fn reset_page(&mut self, index: usize);
 
-    fn run_garbage_collection(&mut self) -> usize {
-        let mut pages = unsafe { self.get_pages_mut() };
+    /// Returns the in-RAM [`KeyIndex`], for implementors that keep one.
+    ///
+    /// The default returns `None`, which makes the index an opt-in feature:
+    /// `read_variable` falls back to scanning the active page when there is
+    /// no index to consult, or when the index doesn't have the key cached.
+    fn index(&self) -> Option<&KeyIndex> {
+        None
+    }
+
+    /// Mutable counterpart to [`index`](Self::index).
+    fn index_mut(&mut self) -> Option<&mut KeyIndex> {
+        None
+    }
+
+    /// Rebuilds the key index from scratch by scanning the active page once.
+    ///
+    /// A no-op if this implementor doesn't keep an index. `write_variable`,
+    /// `run_garbage_collection`, and `recover_gc` all keep the index in sync
+    /// incrementally as they run (the same logic [`AsyncEEPROM`] uses), so
+    /// this is only needed to (re)synchronize an index against page state it
+    /// never saw written - e.g. right after attaching one to a store that
+    /// already has data on it.
+    fn rebuild_index(&mut self) -> Result<(), EepromError> {
+        if self.index().is_none() {
+            return Ok(());
+        }
+
+        let pages = unsafe { self.get_pages() };
 
         let mut maybe_active_page_index = None;
 
@@ -55,73 +115,63 @@ pub trait EEPROM<const N: usize> {
                     maybe_active_page_index = Some(index);
                     break;
                 }
-                _ => panic!("run_garbage_collection: invalid page header {}", page[0])
+                value => return Err(EepromError::CorruptPageHeader { index, value })
             }
         };
 
-        let active_page_index = if let Some(n) = maybe_active_page_index {
-            n
-        } else {
-            for i in 0..pages.len() {
-                unsafe { self.reset_page(i); }
-            }
-
-            pages = unsafe { self.get_pages_mut() };
-            pages[0][0] = 1;
-            0
-        };
-
-        let next_page_index = if active_page_index + 1 == pages.len() {
-            0
-        } else {
-            active_page_index + 1
-        };
-
-        let (active_page, next_page) = get_two_mut(&mut pages, active_page_index, next_page_index);
-
-        assert_eq!(next_page[0], Erased as u8);
-
-        active_page[0] = GcRunning as u8;
+        let mut table: KeyIndex = [None; INDEX_CAPACITY];
 
-        let mut active_index = 1;
-        let mut next_index = 1;
+        if let Some(active_page_index) = maybe_active_page_index {
+            let page = pages[active_page_index];
+            let mut index = 1;
 
-        // Copy the variables from the active page into the next page
-        loop {
-            let variable: Variable = active_page[active_index..active_index + 5].into();
-
-            match variable.address {
-                core::u8::MAX => break,
-                0 => active_index = active_index + 5 + variable.size as usize,
-                _ => {
-                    let data = &active_page[active_index + 5..active_index + 5 + variable.size as usize];
-
-                    next_page[next_index..next_index + 5].copy_from_slice(&active_page[active_index..active_index + 5]);
-                    next_page[next_index + 5..next_index + 5 + variable.size as usize].copy_from_slice(&data);
-
-                    next_index = next_index + 5 + variable.size as usize;
+            loop {
+                match scan_record(page, index) {
+                    Record::End => break,
+                    Record::Dead { next_index } => index = next_index,
+                    Record::Live { key, next_index, .. } => {
+                        insert_into_index(&mut table, key, index as u32);
+                        index = next_index;
+                    }
                 }
             }
         }
 
+        if let Some(slot) = self.index_mut() {
+            *slot = table;
+        }
 
-        // Reset the active page
-        unsafe { self.reset_page(active_page_index) };
-
-        let pages = unsafe { self.get_pages_mut() };
+        Ok(())
+    }
 
-        // Set the next page to the active state
-        pages[next_page_index][0] = Active as u8;
+    /// Resumes or rolls back an interrupted [`run_garbage_collection`](Self::run_garbage_collection).
+    ///
+    /// If the last reboot happened while a page was marked `GcRunning`, the
+    /// copy into the next page may have been left half-written. This
+    /// validates the destination page's records by CRC, resumes the copy
+    /// from the last verified record, and only then finalizes the
+    /// destination as `Active` and erases the old page - mirroring what
+    /// `run_garbage_collection` itself does when uninterrupted.
+    fn recover_gc(&mut self) -> Result<(), EepromError> {
+        block_on(SyncBridge(self).recover_gc())
+    }
 
-        next_page_index
+    fn run_garbage_collection(&mut self) -> Result<usize, EepromError> {
+        block_on(SyncBridge(self).run_garbage_collection())
     }
 
+    fn write_variable(&mut self, key: Key, data: &[u8]) -> Result<(), EepromError> {
+        block_on(SyncBridge(self).write_variable(key, data))
+    }
 
-    fn write_variable(&mut self, address: u8, data: &[u8]) {
-        assert_ne!(address, 0);
-        assert_ne!(address, core::u8::MAX);
+    /// Unlike [`AsyncEEPROM::read_variable`], this borrows straight out of
+    /// the active page instead of copying into a caller-supplied buffer,
+    /// which a page staged through `block_on` couldn't give back - so this
+    /// one method keeps its own implementation rather than delegating.
+    fn read_variable(&mut self, key: Key) -> Result<Option<&[u8]>, EepromError> {
+        self.recover_gc()?;
 
-        let mut pages = unsafe { self.get_pages_mut() };
+        let pages = unsafe { self.get_pages() };
 
         let mut maybe_active_page_index = None;
 
@@ -132,122 +182,98 @@ pub trait EEPROM<const N: usize> {
                     maybe_active_page_index = Some(index);
                     break;
                 }
-                _ => panic!("write_variable: invalid page header {}", page[0])
+                value => return Err(EepromError::CorruptPageHeader { index, value })
             }
         };
 
-        let active_page_index = if let Some(n) = maybe_active_page_index {
+        let active_page_index: usize = if let Some(n) = maybe_active_page_index {
             n
         } else {
-            for i in 0..pages.len() {
-                unsafe { self.reset_page(i); }
-            }
-
-            pages = unsafe { self.get_pages_mut() };
-            pages[0][0] = 1;
-            0
+            return Err(EepromError::NoActivePage);
         };
 
-        let mut page = &mut pages[active_page_index];
+        let page: &[u8] = pages[active_page_index];
 
-        let mut index = 1;
-        let mut gc_run = false;
+        if let Some(table) = self.index() {
+            if let Some(offset) = lookup_in_index(table, key) {
+                let offset = offset as usize;
 
-        loop {
-            let variable: Variable = page[index..index + 5].into();
-
-            if index + 5 + data.len() > page.len() {
-                if gc_run {
-                    panic!("Not enough space in eeprom to write to address {}", address);
-                } else {
-                    let page_index = self.run_garbage_collection();
-                    pages = unsafe { self.get_pages_mut() };
-                    page = &mut pages[page_index];
-                    index = 1;
-                    gc_run = true;
+                if let Record::Live { key: found, size, .. } = scan_record(page, offset) {
+                    if found == key {
+                        let header_len = header_len(found.len());
+                        return Ok(Some(&page[offset + header_len..offset + header_len + size]));
+                    }
                 }
+                // The indexed offset didn't check out (e.g. the index wasn't rebuilt after an
+                // out-of-band page change) - fall back to the scan-based path below.
             }
-
-            if variable.address == core::u8::MAX {
-                page[index] = address;
-                page[index + 1..index + 5].copy_from_slice(&(data.len() as u32).to_le_bytes());
-                page[index + 5..index + 5 + data.len()].copy_from_slice(data);
-                return;
-            } else if page[index] == address {
-                page[index] = 0;
-                index = index + 5 + variable.size as usize;
-            } else {
-                index = index + 5 + variable.size as usize;
-            }
+            // Not cached doesn't mean absent: the index only remembers up to
+            // INDEX_CAPACITY keys, so a miss here still has to be confirmed
+            // by scanning the page.
         }
-    }
-
-    fn read_variable(&self, address: u8) -> Option<&[u8]> {
-        assert_ne!(address, 0);
-        assert_ne!(address, core::u8::MAX);
-
-        let pages = unsafe { self.get_pages() };
-
-        let mut maybe_active_page_index = None;
-
-        for (index, page) in pages.iter().enumerate() {
-            match page[0] {
-                core::u8::MAX => continue,
-                1 => {
-                    maybe_active_page_index = Some(index);
-                    break;
-                }
-                _ => panic!("read: invalid page header {}", page[0])
-            }
-        };
-
-        let active_page_index: usize = if let Some(n) = maybe_active_page_index {
-            n
-        } else {
-            return None;
-        };
-
-        let page = pages[active_page_index];
 
         let mut index = 1;
 
         loop {
-            let variable: Variable = page[index..index + 5].into();
-
-            if index >= page.len() {
-                return None;
-            }
-
-            if variable.address == core::u8::MAX {
-                return None;
-            } else if variable.address == address {
-                return Some(&page[index + 5..index + 5 + variable.size as usize]);
-            } else {
-                index = index + 5 + variable.size as usize;
+            match scan_record(page, index) {
+                Record::End => return Ok(None),
+                Record::Dead { next_index } => index = next_index,
+                Record::Live { key: existing, size, next_index } => {
+                    if existing == key {
+                        let header_len = header_len(existing.len());
+                        return Ok(Some(&page[index + header_len..index + header_len + size]));
+                    }
+                    index = next_index;
+                }
             }
         }
     }
 }
 
-/// Returns a mutable reference to two elements of a slice
-///
-/// # Panics
-///
-/// Panics if `a` or `b` are out of bounds.
-/// Panics if `a` and `b` are equal.
-fn get_two_mut<T>(slice: &mut [T], a: usize, b: usize) -> (&mut T, &mut T) {
-    assert_ne!(a, b);
+/// Adapts a borrowed [`EEPROM`] implementor into an [`AsyncEEPROM`] whose
+/// "async" operations complete on the first poll, since the underlying
+/// memory is directly addressable. [`EEPROM`]'s defaults drive this through
+/// [`block_on`] so the garbage-collection logic only has to be written
+/// once, in [`AsyncEEPROM`]'s defaults.
+struct SyncBridge<'a, T: ?Sized>(&'a mut T);
+
+impl<'a, T, const N: usize, const PAGE_SIZE: usize> AsyncEEPROM<N, PAGE_SIZE> for SyncBridge<'a, T>
+where
+    T: EEPROM<N, PAGE_SIZE> + ?Sized,
+{
+    async fn read_page(&mut self, index: usize, buf: &mut [u8; PAGE_SIZE]) -> Result<(), EepromError> {
+        let pages = unsafe { self.0.get_pages() };
+        buf.copy_from_slice(pages[index]);
+        Ok(())
+    }
+
+    async fn write_bytes(&mut self, index: usize, offset: usize, data: &[u8]) -> Result<(), EepromError> {
+        let pages = unsafe { self.0.get_pages_mut() };
+        pages[index][offset..offset + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    async fn erase_page(&mut self, index: usize) -> Result<(), EepromError> {
+        unsafe { self.0.reset_page(index) };
+        Ok(())
+    }
 
-    unsafe {
-        let ar = &mut *(slice.get_mut(a).unwrap() as *mut _);
-        let br = &mut *(slice.get_mut(b).unwrap() as *mut _);
-        (ar, br)
+    // Forward to the wrapped `EEPROM`'s index rather than defaulting to
+    // `None`, so the incremental point-updates `AsyncEEPROM`'s defaults make
+    // through these land in the same table `EEPROM::read_variable` consults.
+    fn index(&self) -> Option<&KeyIndex> {
+        self.0.index()
+    }
+
+    fn index_mut(&mut self) -> Option<&mut KeyIndex> {
+        self.0.index_mut()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::EEPROM;
+    use crate::{EEPROM, EepromError, Key, MAX_KEY_LEN, INDEX_CAPACITY};
+    use crate::PageHeader::GcRunning;
 
     struct ArrayEEPROM {
         pages: [[u8; 4096]; 3]
@@ -259,12 +285,41 @@ mod tests {
         }
     }
 
-    impl EEPROM<3> for ArrayEEPROM {
-        unsafe fn get_pages(&self) -> [&[u8]; 3] {
+    impl EEPROM<3, 4096> for ArrayEEPROM {
+        unsafe fn get_pages(&self) -> [&[u8; 4096]; 3] {
+            [&self.pages[0], &self.pages[1], &self.pages[2]]
+        }
+
+        unsafe fn get_pages_mut(&mut self) -> [&mut [u8; 4096]; 3] {
+            [
+                &mut *(self.pages.get_unchecked_mut(0) as *mut _),
+                &mut *(self.pages.get_unchecked_mut(1) as *mut _),
+                &mut *(self.pages.get_unchecked_mut(2) as *mut _)
+            ]
+        }
+
+        unsafe fn reset_page(&mut self, index: usize) {
+            self.pages[index] = [core::u8::MAX; 4096];
+        }
+    }
+
+    struct IndexedEEPROM {
+        pages: [[u8; 4096]; 3],
+        index: crate::KeyIndex,
+    }
+
+    impl IndexedEEPROM {
+        fn new() -> IndexedEEPROM {
+            IndexedEEPROM { pages: [[core::u8::MAX; 4096]; 3], index: [None; INDEX_CAPACITY] }
+        }
+    }
+
+    impl EEPROM<3, 4096> for IndexedEEPROM {
+        unsafe fn get_pages(&self) -> [&[u8; 4096]; 3] {
             [&self.pages[0], &self.pages[1], &self.pages[2]]
         }
 
-        unsafe fn get_pages_mut(&mut self) -> [&mut [u8]; 3] {
+        unsafe fn get_pages_mut(&mut self) -> [&mut [u8; 4096]; 3] {
             [
                 &mut *(self.pages.get_unchecked_mut(0) as *mut _),
                 &mut *(self.pages.get_unchecked_mut(1) as *mut _),
@@ -275,6 +330,14 @@ mod tests {
         unsafe fn reset_page(&mut self, index: usize) {
             self.pages[index] = [core::u8::MAX; 4096];
         }
+
+        fn index(&self) -> Option<&crate::KeyIndex> {
+            Some(&self.index)
+        }
+
+        fn index_mut(&mut self) -> Option<&mut crate::KeyIndex> {
+            Some(&mut self.index)
+        }
     }
 
     #[test]
@@ -282,24 +345,32 @@ mod tests {
         let mut eeprom: ArrayEEPROM = ArrayEEPROM::new();
         let data = [1, 2, 3, 4];
 
-        eeprom.write_variable(1, &data);
-        assert_eq!(eeprom.read_variable(1).unwrap(), &data)
+        eeprom.write_variable(1u8.into(), &data).unwrap();
+        assert_eq!(eeprom.read_variable(1u8.into()).unwrap().unwrap(), &data)
+    }
+
+    #[test]
+    fn read_missing_on_uninitialized_store_errors() {
+        let mut eeprom: ArrayEEPROM = ArrayEEPROM::new();
+
+        assert_eq!(eeprom.read_variable(1u8.into()), Err(EepromError::NoActivePage))
     }
 
     #[test]
-    fn read_missing_returns_none() {
-        let eeprom: ArrayEEPROM = ArrayEEPROM::new();
+    fn read_missing_on_initialized_store_returns_none() {
+        let mut eeprom: ArrayEEPROM = ArrayEEPROM::new();
+
+        eeprom.write_variable(1u8.into(), &[1, 2, 3, 4]).unwrap();
 
-        assert_eq!(eeprom.read_variable(1), None)
+        assert_eq!(eeprom.read_variable(2u8.into()).unwrap(), None)
     }
 
     #[test]
-    #[should_panic]
-    fn write_too_much() {
+    fn write_too_much_is_variable_too_large() {
         let mut eeprom: ArrayEEPROM = ArrayEEPROM::new();
         let data = [1; 4097];
 
-        eeprom.write_variable(1, &data);
+        assert_eq!(eeprom.write_variable(1u8.into(), &data), Err(EepromError::VariableTooLarge));
     }
 
     #[test]
@@ -307,42 +378,107 @@ mod tests {
         let mut eeprom: ArrayEEPROM = ArrayEEPROM::new();
 
         for i in 0..16 {
-            eeprom.write_variable(1, &[i; 512]);
-            for j in eeprom.read_variable(1).unwrap() {
+            eeprom.write_variable(1u8.into(), &[i; 512]).unwrap();
+            for j in eeprom.read_variable(1u8.into()).unwrap().unwrap() {
                 assert_eq!(j, &i);
             }
         }
     }
 
     #[test]
-    #[should_panic]
-    fn write_address_zero_panics() {
+    fn empty_key_is_invalid() {
+        assert_eq!(Key::new(&[]), Err(EepromError::InvalidKey));
+    }
+
+    #[test]
+    fn oversized_key_is_invalid() {
+        assert_eq!(Key::new(&[0; MAX_KEY_LEN + 1]), Err(EepromError::InvalidKey));
+    }
+
+    #[test]
+    fn byte_string_keys_round_trip() {
+        let mut eeprom: ArrayEEPROM = ArrayEEPROM::new();
+
+        eeprom.write_variable(Key::new(b"hello").unwrap(), &[1, 2, 3]).unwrap();
+
+        assert_eq!(eeprom.read_variable(Key::new(b"hello").unwrap()).unwrap().unwrap(), &[1, 2, 3]);
+        assert_eq!(eeprom.read_variable(Key::new(b"world").unwrap()).unwrap(), None);
+    }
+
+    #[test]
+    fn corrupt_record_crc_is_treated_as_end_of_page() {
         let mut eeprom: ArrayEEPROM = ArrayEEPROM::new();
 
-        eeprom.write_variable(0, &[1]);
+        eeprom.write_variable(1u8.into(), &[1, 2, 3, 4]).unwrap();
+        // Flip a data byte after the fact, without touching the stored CRC.
+        eeprom.pages[0][11] ^= 0xFF;
+
+        assert_eq!(eeprom.read_variable(1u8.into()).unwrap(), None);
     }
 
     #[test]
-    #[should_panic]
-    fn write_address_max_panics() {
+    fn recovers_a_gc_interrupted_before_the_copy_finished() {
         let mut eeprom: ArrayEEPROM = ArrayEEPROM::new();
 
-        eeprom.write_variable(core::u8::MAX, &[1]);
+        eeprom.write_variable(1u8.into(), &[1, 2, 3, 4]).unwrap();
+        eeprom.write_variable(2u8.into(), &[5, 6, 7, 8]).unwrap();
+
+        // Simulate a reboot mid-garbage-collection: page 0 still active, marked GcRunning,
+        // and the destination page never got past its header.
+        eeprom.pages[0][0] = GcRunning as u8;
+
+        eeprom.write_variable(3u8.into(), &[9, 9, 9, 9]).unwrap();
+
+        assert_eq!(eeprom.read_variable(1u8.into()).unwrap().unwrap(), &[1, 2, 3, 4]);
+        assert_eq!(eeprom.read_variable(2u8.into()).unwrap().unwrap(), &[5, 6, 7, 8]);
+        assert_eq!(eeprom.read_variable(3u8.into()).unwrap().unwrap(), &[9, 9, 9, 9]);
     }
 
     #[test]
-    #[should_panic]
-    fn read_address_zero_panics() {
-        let eeprom: ArrayEEPROM = ArrayEEPROM::new();
+    fn indexed_read_finds_latest_write() {
+        let mut eeprom = IndexedEEPROM::new();
 
-        eeprom.read_variable(0);
+        eeprom.write_variable(1u8.into(), &[1, 2, 3, 4]).unwrap();
+        eeprom.write_variable(1u8.into(), &[5, 6, 7, 8]).unwrap();
+
+        assert_eq!(eeprom.read_variable(1u8.into()).unwrap().unwrap(), &[5, 6, 7, 8]);
+        assert_eq!(eeprom.read_variable(2u8.into()).unwrap(), None);
+    }
+
+    #[test]
+    fn indexed_read_survives_garbage_collection() {
+        let mut eeprom = IndexedEEPROM::new();
+
+        for i in 0..16 {
+            eeprom.write_variable(1u8.into(), &[i; 512]).unwrap();
+            for j in eeprom.read_variable(1u8.into()).unwrap().unwrap() {
+                assert_eq!(j, &i);
+            }
+        }
     }
 
     #[test]
-    #[should_panic]
-    fn read_address_max_panics() {
-        let eeprom: ArrayEEPROM = ArrayEEPROM::new();
+    fn rebuild_index_picks_up_state_written_before_the_index_was_attached() {
+        let mut eeprom = IndexedEEPROM::new();
 
-        eeprom.read_variable(core::u8::MAX);
+        eeprom.write_variable(1u8.into(), &[1, 2, 3, 4]).unwrap();
+        eeprom.index = [None; INDEX_CAPACITY];
+
+        eeprom.rebuild_index().unwrap();
+
+        assert_eq!(eeprom.read_variable(1u8.into()).unwrap().unwrap(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn index_falls_back_to_scan_once_capacity_is_exceeded() {
+        let mut eeprom = IndexedEEPROM::new();
+
+        for i in 0..(INDEX_CAPACITY as u16 + 1) {
+            eeprom.write_variable(i.into(), &[i as u8]).unwrap();
+        }
+
+        for i in 0..(INDEX_CAPACITY as u16 + 1) {
+            assert_eq!(eeprom.read_variable(i.into()).unwrap().unwrap(), &[i as u8]);
+        }
     }
 }
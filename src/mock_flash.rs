@@ -0,0 +1,328 @@
+use crate::error::EepromError;
+use crate::{AsyncEEPROM, KeyIndex, INDEX_CAPACITY};
+
+/// What a pending power-loss injection does to the operation that triggers it.
+enum Tick {
+    /// No injection is armed, or the device is mid-countdown: proceed as normal.
+    Ok,
+    /// This is the injected operation: apply only part of it, then go dark.
+    Torn,
+    /// The device is already dark from an earlier injection: do nothing.
+    Dead,
+}
+
+/// A flash model that enforces the invariants real NOR flash imposes and a
+/// plain byte array never does, plus optional power-loss injection, so a
+/// fuzz harness can assert the crate's crash-recovery claims instead of
+/// just trusting them.
+///
+/// `N` is the page count, `PAGE` the page size in bytes, and `WORD` the
+/// smallest unit flash actually programs. `write_bytes` accepts any byte
+/// range a caller asks for - this crate's record format packs headers and
+/// data back to back, so callers have no reason to stick to `WORD`
+/// boundaries themselves - and pads it out to the enclosing word(s) with
+/// the page's existing bytes before applying it, mirroring how a real flash
+/// driver rounds a sub-word program request up to whole words under the
+/// hood. Every write may still only clear bits (`1 -> 0`); only
+/// [`erase_page`](AsyncEEPROM::erase_page) may set them back to `1`.
+pub struct MockFlash<const N: usize, const PAGE: usize, const WORD: usize> {
+    pages: [[u8; PAGE]; N],
+    /// Remaining underlying `write_bytes`/`erase_page` calls before a
+    /// simulated power loss; `None` means no injection is armed.
+    ops_until_power_loss: Option<usize>,
+    /// `false` once an armed power loss has fired, modelling the device
+    /// staying off until [`reboot`](Self::reboot) is called.
+    powered_on: bool,
+    /// The in-RAM key index, if this instance was built with one - see
+    /// [`with_index`](Self::with_index).
+    index: Option<KeyIndex>,
+}
+
+impl<const N: usize, const PAGE: usize, const WORD: usize> Default for MockFlash<N, PAGE, WORD> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const PAGE: usize, const WORD: usize> MockFlash<N, PAGE, WORD> {
+    pub fn new() -> Self {
+        MockFlash { pages: [[core::u8::MAX; PAGE]; N], ops_until_power_loss: None, powered_on: true, index: None }
+    }
+
+    /// Like [`new`](Self::new), but with an in-RAM key index attached, so
+    /// [`AsyncEEPROM::read_variable`] can look keys up in O(1) instead of
+    /// scanning the active page.
+    pub fn with_index() -> Self {
+        MockFlash { index: Some([None; INDEX_CAPACITY]), ..Self::new() }
+    }
+
+    /// Arms power-loss injection: the `n`th underlying write or erase from
+    /// now aborts partway through, leaving only some of its bytes applied,
+    /// and every operation after that is a no-op until [`reboot`](Self::reboot).
+    pub fn inject_power_loss_after(&mut self, n: usize) {
+        self.ops_until_power_loss = Some(n);
+    }
+
+    /// `true` once an armed power loss has fired.
+    pub fn power_lost(&self) -> bool {
+        !self.powered_on
+    }
+
+    /// Brings the device back up without erasing anything, so a fuzz
+    /// harness can simulate a reboot and then exercise `recover_gc`
+    /// against whatever state the torn operation left behind.
+    pub fn reboot(&mut self) {
+        self.powered_on = true;
+        self.ops_until_power_loss = None;
+    }
+
+    fn tick(&mut self) -> Tick {
+        if !self.powered_on {
+            return Tick::Dead;
+        }
+
+        match self.ops_until_power_loss {
+            Some(0) => {
+                self.powered_on = false;
+                Tick::Torn
+            }
+            Some(n) => {
+                self.ops_until_power_loss = Some(n - 1);
+                Tick::Ok
+            }
+            None => Tick::Ok,
+        }
+    }
+}
+
+impl<const N: usize, const PAGE: usize, const WORD: usize> AsyncEEPROM<N, PAGE> for MockFlash<N, PAGE, WORD> {
+    async fn read_page(&mut self, index: usize, buf: &mut [u8; PAGE]) -> Result<(), EepromError> {
+        buf.copy_from_slice(&self.pages[index]);
+        Ok(())
+    }
+
+    async fn write_bytes(&mut self, index: usize, offset: usize, data: &[u8]) -> Result<(), EepromError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        // Round the requested range out to the enclosing word(s), padding
+        // with the page's own existing bytes - writing a byte back as
+        // itself is always a legal no-op bit-wise, so this never turns an
+        // otherwise-legal request into an illegal one.
+        let word_start = (offset / WORD) * WORD;
+        let word_end = ((offset + data.len() + WORD - 1) / WORD) * WORD;
+        let span = word_end - word_start;
+
+        let mut padded = [0u8; PAGE];
+        padded[..span].copy_from_slice(&self.pages[index][word_start..word_end]);
+        padded[offset - word_start..offset - word_start + data.len()].copy_from_slice(data);
+
+        for (&old, &new) in self.pages[index][word_start..word_end].iter().zip(&padded[..span]) {
+            assert_eq!(!old & new, 0, "write at offset {} would set a bit from 0 back to 1 without an erase", word_start);
+        }
+
+        // `tick` only touches `ops_until_power_loss`/`powered_on`, but borrows `self`
+        // mutably, so it has to run before `page` borrows `self.pages[index]` - an
+        // earlier version held that borrow across this call and didn't build.
+        let outcome = self.tick();
+        let page = &mut self.pages[index];
+
+        match outcome {
+            Tick::Dead => Ok(()),
+            Tick::Torn => {
+                // Torn mid-write: only the first half of the words made it out.
+                let torn_len = (span / 2 / WORD) * WORD;
+                page[word_start..word_start + torn_len].copy_from_slice(&padded[..torn_len]);
+                Ok(())
+            }
+            Tick::Ok => {
+                page[word_start..word_end].copy_from_slice(&padded[..span]);
+                Ok(())
+            }
+        }
+    }
+
+    async fn erase_page(&mut self, index: usize) -> Result<(), EepromError> {
+        match self.tick() {
+            Tick::Dead => Ok(()),
+            Tick::Torn => {
+                // Torn mid-erase: only the first half of the page was reset.
+                self.pages[index][..PAGE / 2].fill(core::u8::MAX);
+                Ok(())
+            }
+            Tick::Ok => {
+                self.pages[index] = [core::u8::MAX; PAGE];
+                Ok(())
+            }
+        }
+    }
+
+    fn index(&self) -> Option<&KeyIndex> {
+        self.index.as_ref()
+    }
+
+    fn index_mut(&mut self) -> Option<&mut KeyIndex> {
+        self.index.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockFlash;
+    use crate::{block_on, AsyncEEPROM};
+
+    #[test]
+    fn write_and_read_round_trip() {
+        let mut flash: MockFlash<3, 4096, 4> = MockFlash::new();
+
+        block_on(flash.write_variable(1u8.into(), &[1, 2, 3, 4])).unwrap();
+
+        let mut out = [0u8; 4];
+        let len = block_on(flash.read_variable(1u8.into(), &mut out)).unwrap().unwrap();
+        assert_eq!(&out[..len], &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn overwriting_an_existing_key_does_not_require_setting_a_bit() {
+        let mut flash: MockFlash<3, 4096, 4> = MockFlash::new();
+
+        block_on(flash.write_variable(1u8.into(), &[1, 2, 3, 4])).unwrap();
+        // Tombstoning the old record would panic here if it needed a 0 -> 1
+        // bit transition, same as `rewriting_a_cleared_bit_without_an_erase_panics` below.
+        block_on(flash.write_variable(1u8.into(), &[5, 6, 7, 8])).unwrap();
+
+        let mut out = [0u8; 4];
+        let len = block_on(flash.read_variable(1u8.into(), &mut out)).unwrap().unwrap();
+        assert_eq!(&out[..len], &[5, 6, 7, 8]);
+
+        // Confirm the old record's length byte was actually cleared in
+        // place rather than this passing for some other reason - the first
+        // record's header starts right after the page header byte, at
+        // index 1, and a 1-byte key gives it a 10-byte header, so with its
+        // 4 data bytes the new record starts at index 15.
+        let mut page = [0u8; 4096];
+        block_on(flash.read_page(0, &mut page)).unwrap();
+        assert_eq!(page[1], 1, "old record's live bit should be cleared, not erased");
+        assert_eq!(page[15] & 0x80, 0x80, "new record's live bit should still be set");
+    }
+
+    #[test]
+    fn misaligned_writes_are_padded_out_to_whole_words() {
+        let mut flash: MockFlash<3, 4096, 4> = MockFlash::new();
+
+        // Neither the offset nor the length here is a multiple of WORD; a
+        // real flash driver would pad this out to whole words under the
+        // hood rather than reject it, and so does MockFlash.
+        block_on(flash.write_bytes(0, 1, &[1, 2, 3])).unwrap();
+
+        let mut buf = [0u8; 4096];
+        block_on(flash.read_page(0, &mut buf)).unwrap();
+        assert_eq!(&buf[1..4], &[1, 2, 3]);
+        // Byte 0 was pulled in to complete the enclosing word, but wasn't
+        // part of the request, so it's left exactly as it was.
+        assert_eq!(buf[0], core::u8::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "set a bit from 0 back to 1")]
+    fn rewriting_a_cleared_bit_without_an_erase_panics() {
+        let mut flash: MockFlash<3, 4096, 4> = MockFlash::new();
+        block_on(flash.write_bytes(0, 0, &[0, 0, 0, 0])).unwrap();
+        block_on(flash.write_bytes(0, 0, &[1, 0, 0, 0])).unwrap();
+    }
+
+    #[test]
+    fn erase_clears_the_page_back_to_writable() {
+        let mut flash: MockFlash<3, 4096, 4> = MockFlash::new();
+        block_on(flash.write_bytes(0, 0, &[0, 0, 0, 0])).unwrap();
+        block_on(flash.erase_page(0)).unwrap();
+        // Would have panicked before the erase.
+        block_on(flash.write_bytes(0, 0, &[1, 2, 3, 4])).unwrap();
+    }
+
+    #[test]
+    fn power_loss_tears_the_nth_operation_and_then_goes_dark() {
+        let mut flash: MockFlash<3, 4096, 4> = MockFlash::new();
+        flash.inject_power_loss_after(0);
+
+        block_on(flash.write_bytes(0, 0, &[1, 2, 3, 4, 5, 6, 7, 8])).unwrap();
+        let mut buf = [0u8; 4096];
+        block_on(flash.read_page(0, &mut buf)).unwrap();
+        assert_eq!(&buf[..4], &[1, 2, 3, 4]);
+        // The second half of the torn write never made it out, so it's still erased.
+        assert_eq!(&buf[4..8], &[core::u8::MAX; 4]);
+        assert!(flash.power_lost());
+
+        // The device is dark: further writes have no effect...
+        block_on(flash.write_bytes(0, 8, &[9, 9, 9, 9])).unwrap();
+        block_on(flash.read_page(0, &mut buf)).unwrap();
+        assert_eq!(&buf[8..12], &[core::u8::MAX; 4]);
+
+        // ...until it's rebooted.
+        flash.reboot();
+        block_on(flash.write_bytes(0, 8, &[9, 9, 9, 9])).unwrap();
+        block_on(flash.read_page(0, &mut buf)).unwrap();
+        assert_eq!(&buf[8..12], &[9, 9, 9, 9]);
+    }
+
+    /// Exercises the request's actual deliverable: hammer `write_variable`
+    /// and `run_garbage_collection` with power loss injected at every
+    /// possible operation, and check that every variable committed before
+    /// the simulated reset is still readable afterwards through
+    /// `recover_gc` - not just that a single raw `write_bytes`/`erase_page`
+    /// call tears cleanly, which the test above already covers.
+    #[test]
+    fn every_variable_committed_before_a_power_loss_survives_recovery() {
+        for n in 0..200 {
+            let mut flash: MockFlash<3, 4096, 4> = MockFlash::new();
+
+            block_on(flash.write_variable(1u8.into(), &[1, 2, 3, 4])).unwrap();
+            block_on(flash.write_variable(2u8.into(), &[5, 6, 7, 8])).unwrap();
+
+            flash.inject_power_loss_after(n);
+
+            // One of these is the torn operation (or none, once `n` runs
+            // past how many underlying writes/erases the sequence takes);
+            // either way the device goes dark from then on and every
+            // further call here is a no-op until `reboot`.
+            let _ = block_on(flash.write_variable(3u8.into(), &[9, 9, 9, 9]));
+            let _ = block_on(flash.run_garbage_collection());
+
+            flash.reboot();
+            block_on(flash.recover_gc()).unwrap();
+
+            let mut out = [0u8; 4];
+            let len = block_on(flash.read_variable(1u8.into(), &mut out)).unwrap().unwrap();
+            assert_eq!(&out[..len], &[1, 2, 3, 4], "n = {}", n);
+
+            let len = block_on(flash.read_variable(2u8.into(), &mut out)).unwrap().unwrap();
+            assert_eq!(&out[..len], &[5, 6, 7, 8], "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn indexed_read_finds_latest_write() {
+        let mut flash: MockFlash<3, 4096, 4> = MockFlash::with_index();
+
+        block_on(flash.write_variable(1u8.into(), &[1, 2, 3, 4])).unwrap();
+        block_on(flash.write_variable(1u8.into(), &[5, 6, 7, 8])).unwrap();
+
+        let mut out = [0u8; 4];
+        let len = block_on(flash.read_variable(1u8.into(), &mut out)).unwrap().unwrap();
+        assert_eq!(&out[..len], &[5, 6, 7, 8]);
+        assert_eq!(block_on(flash.read_variable(2u8.into(), &mut out)).unwrap(), None);
+    }
+
+    #[test]
+    fn indexed_read_survives_garbage_collection() {
+        let mut flash: MockFlash<3, 4096, 4> = MockFlash::with_index();
+
+        for i in 0..16u8 {
+            block_on(flash.write_variable(1u8.into(), &[i; 512])).unwrap();
+            let mut out = [0u8; 512];
+            let len = block_on(flash.read_variable(1u8.into(), &mut out)).unwrap().unwrap();
+            assert_eq!(&out[..len], &[i; 512]);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::crc::crc32;
+use crate::key::{Key, MAX_KEY_LEN};
+
+/// Set in a live record's length byte, cleared to tombstone it.
+///
+/// This has to be the bit that's *cleared* to mark a record dead rather than
+/// one that's *set*, because flash can only ever clear bits once they're
+/// written - [`encode_header`] writes every new record's length byte with
+/// this bit already set, so tombstoning only ever needs a legal `1 -> 0`
+/// write, never an erase. The remaining bits still hold the key's length, so
+/// the record's on-disk size - and therefore the stride to the next record -
+/// stays computable after tombstoning.
+const LIVE_BIT: u8 = 0x80;
+
+/// `len(1) + key(len) + size(4) + crc32(4)`, followed by `size` bytes of data.
+pub(crate) const MAX_HEADER_LEN: usize = 1 + MAX_KEY_LEN + 4 + 4;
+
+/// The on-disk header length for a key of `key_len` bytes.
+pub(crate) fn header_len(key_len: u8) -> usize {
+    1 + key_len as usize + 4 + 4
+}
+
+/// The result of inspecting one record slot inside a page.
+pub(crate) enum Record {
+    /// An erased (`0xFF`) length byte, or a record whose CRC does not
+    /// verify. Either way nothing usable follows, so scanning should stop
+    /// here.
+    End,
+    /// A tombstoned record (its length byte's live bit was cleared by a
+    /// later write); skip over it.
+    Dead { next_index: usize },
+    /// A CRC-verified live record.
+    Live { key: Key, size: usize, next_index: usize },
+}
+
+/// Inspects the record at `index` in `page`.
+///
+/// A record whose CRC fails to verify is treated the same as an erased
+/// (never-written) slot, which also covers a torn write of an individual
+/// variable: `End` is returned and the caller stops scanning rather than
+/// trusting a partially-written record.
+pub(crate) fn scan_record(page: &[u8], index: usize) -> Record {
+    if index >= page.len() {
+        return Record::End;
+    }
+
+    let raw = page[index];
+
+    if raw == core::u8::MAX {
+        return Record::End;
+    }
+
+    let dead = raw & LIVE_BIT == 0;
+    let key_len = raw & !LIVE_BIT;
+
+    if key_len == 0 || key_len as usize > MAX_KEY_LEN {
+        // A corrupt length byte - no different than a failed CRC below.
+        return Record::End;
+    }
+
+    let header_len = header_len(key_len);
+
+    if index + header_len > page.len() {
+        return Record::End;
+    }
+
+    let key_bytes = &page[index + 1..index + 1 + key_len as usize];
+    let size = LittleEndian::read_u32(&page[index + 1 + key_len as usize..index + header_len - 4]) as usize;
+    let next_index = index + header_len + size;
+
+    if next_index > page.len() {
+        return Record::End;
+    }
+
+    if dead {
+        return Record::Dead { next_index };
+    }
+
+    let stored_crc = LittleEndian::read_u32(&page[index + header_len - 4..index + header_len]);
+    let data = &page[index + header_len..next_index];
+    let actual_crc = crc32(&[&[key_len], key_bytes, &(size as u32).to_le_bytes(), data]);
+
+    if stored_crc != actual_crc {
+        return Record::End;
+    }
+
+    Record::Live { key: Key::from_validated(key_bytes), size, next_index }
+}
+
+/// Encodes the header for a new record, including the CRC over
+/// `key_len + key + size + data`.
+///
+/// Returns the header buffer along with how many of its leading bytes are
+/// actually used, since the header length depends on the key's length.
+pub(crate) fn encode_header(key: &Key, data: &[u8]) -> ([u8; MAX_HEADER_LEN], usize) {
+    let key_bytes = key.as_bytes();
+    let key_len = key.len();
+    let size = (data.len() as u32).to_le_bytes();
+    let crc = crc32(&[&[key_len], key_bytes, &size, data]).to_le_bytes();
+
+    let used = header_len(key_len);
+    let mut header = [0u8; MAX_HEADER_LEN];
+    header[0] = key_len | LIVE_BIT;
+    header[1..1 + key_bytes.len()].copy_from_slice(key_bytes);
+    header[1 + key_bytes.len()..used - 4].copy_from_slice(&size);
+    header[used - 4..used].copy_from_slice(&crc);
+    (header, used)
+}
+
+/// Marks a record dead in place by clearing [`LIVE_BIT`] in its length byte,
+/// keeping the length bits intact so later scans can still skip over it.
+/// Callers write this back over the record's existing length byte, which is
+/// always a legal `1 -> 0` flash write since `LIVE_BIT` starts set.
+pub(crate) fn dead_marker(key_len: u8) -> u8 {
+    key_len
+}